@@ -0,0 +1,49 @@
+/*!
+`yap` is a "yet another parser" combinator-ish crate. Rather than handing you a
+pile of parser combinators, it hands you a [`Tokens`] trait implemented for
+common input types, and lets you write parsers as plain functions using
+ordinary Rust (`if`, `while`, iterator adapters and so on) over that trait.
+
+See the [`types`] module for the concrete [`Tokens`] implementations this
+crate ships.
+*/
+pub mod types;
+
+/// Anything which can be turned into a [`Tokens`] implementation of the
+/// given `Item` type. This is the entry point for parsing: call
+/// `.into_tokens()` on your input to get something you can parse from.
+pub trait IntoTokens<Item> {
+    /// The [`Tokens`] impl that parsing happens across.
+    type Tokens: Tokens<Item = Item>;
+
+    /// Turn `self` into some [`Tokens`] impl.
+    fn into_tokens(self) -> Self::Tokens;
+}
+
+/// This is the interface that parsers are written against. It's implemented
+/// for the common input types in the [`types`] module, and can be
+/// implemented for your own input types too.
+pub trait Tokens: Iterator + Sized {
+    /// An opaque position in the token stream. Cheap to obtain via
+    /// [`Tokens::location`] and stash away, so that combinators can record
+    /// where a failure happened without paying for anything more expensive
+    /// until an error actually needs to be reported.
+    type Location: core::fmt::Debug + Copy + PartialEq + Eq;
+
+    /// An opaque checkpoint which can be used to rewind the token stream
+    /// back to the position it was saved at, via
+    /// [`Tokens::rewind_to_checkpoint`].
+    type CheckPoint;
+
+    /// Return the current location in the token stream.
+    fn location(&self) -> Self::Location;
+
+    /// Save a checkpoint of the current position, which can be used to
+    /// rewind back to this position later via
+    /// [`Tokens::rewind_to_checkpoint`].
+    fn save_checkpoint(&self) -> Self::CheckPoint;
+
+    /// Rewind back to a position saved earlier via
+    /// [`Tokens::save_checkpoint`].
+    fn rewind_to_checkpoint(&mut self, checkpoint: Self::CheckPoint);
+}
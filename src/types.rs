@@ -7,25 +7,33 @@ Prefer to remain generic where possible, for example by using
 `t: impl Tokens<char>` over `t: StrTokens<'a>` as an argument.
 */
 use super::{ IntoTokens, Tokens };
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
 
 /// This is what we are given back if we call `into_tokens()` on
 /// a `&[T]`. It implements the [`Tokens`] interface.
 pub struct SliceTokens<'a, Item> {
     slice: &'a [Item],
     cursor: usize,
+    // Exclusive upper bound of the unconsumed region; shrinks as items are
+    // consumed from the back via `next_back()`. Starts at `slice.len()`.
+    back: usize,
 }
 
-pub struct SliceTokensCheckpoint(usize);
+pub struct SliceTokensCheckpoint(usize, usize);
 
 impl <'a, Item> SliceTokens<'a, Item> {
-    /// Return the parsed portion of the slice.
+    /// Return the portion of the slice consumed from the front.
     pub fn consumed(&self) -> &'a [Item] {
         &self.slice[..self.cursor]
     }
 
-    /// Return the unparsed remainder of the slice.
+    /// Return the unparsed remainder of the slice, ie the region between
+    /// whatever has been consumed from the front (via `next()`) and the
+    /// back (via `next_back()`).
     pub fn remaining(&self) -> &'a [Item] {
-        &self.slice[self.cursor..]
+        &self.slice[self.cursor..self.back]
     }
 }
 
@@ -38,20 +46,38 @@ impl <'a, Item> From<SliceTokens<'a, Item>> for &'a [Item] {
 impl <'a, Item> Iterator for SliceTokens<'a, Item> {
     type Item = &'a Item;
     fn next(&mut self) -> Option<Self::Item> {
-        let res = self.slice.get(self.cursor);
+        if self.cursor == self.back {
+            return None;
+        }
+        let res = &self.slice[self.cursor];
         self.cursor += 1;
-        res
+        Some(res)
+    }
+}
+
+impl <'a, Item> DoubleEndedIterator for SliceTokens<'a, Item> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.cursor == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(&self.slice[self.back])
     }
 }
 
 impl <'a, Item> Tokens for SliceTokens<'a, Item> {
+    type Location = usize;
     type CheckPoint = SliceTokensCheckpoint;
 
+    fn location(&self) -> Self::Location {
+        self.cursor
+    }
     fn save_checkpoint(&self) -> Self::CheckPoint {
-        SliceTokensCheckpoint(self.cursor)
+        SliceTokensCheckpoint(self.cursor, self.back)
     }
     fn rewind_to_checkpoint(&mut self, checkpoint: Self::CheckPoint) {
         self.cursor = checkpoint.0;
+        self.back = checkpoint.1;
     }
 }
 
@@ -66,6 +92,7 @@ impl <'a, Item> IntoTokens<&'a Item> for &'a [Item] {
     type Tokens = SliceTokens<'a, Item>;
     fn into_tokens(self: Self) -> Self::Tokens {
         SliceTokens {
+            back: self.len(),
             slice: self,
             cursor: 0,
         }
@@ -76,20 +103,55 @@ impl <'a, Item> IntoTokens<&'a Item> for &'a [Item] {
 /// a `&str`. It implements the [`Tokens`] interface.
 pub struct StrTokens<'a> {
     str: &'a str,
-    cursor: usize
+    cursor: usize,
+    // Exclusive upper bound (byte offset) of the unconsumed region; shrinks
+    // as chars are consumed from the back via `next_back()`. Starts at
+    // `str.len()`.
+    back: usize,
 }
 
-pub struct StrTokensCheckpoint(usize);
+pub struct StrTokensCheckpoint(usize, usize);
+
+/// An opaque position within a [`StrTokens`], obtained via
+/// [`Tokens::location`]. It's just a wrapped byte offset, so it's trivially
+/// cheap to capture; convert it into a human readable `(line, column)` pair
+/// with [`StrTokens::line_col`] only once an error actually needs reporting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrTokensLocation(usize);
 
 impl <'a> StrTokens<'a> {
-    /// Return the parsed portion of the str.
+    /// Return the portion of the str consumed from the front.
     pub fn consumed(&self) -> &'a str {
         &self.str[..self.cursor]
     }
 
-    /// Return the unparsed remainder of the str.
+    /// Return the unparsed remainder of the str, ie the region between
+    /// whatever has been consumed from the front (via `next()`) and the
+    /// back (via `next_back()`).
     pub fn remaining(&self) -> &'a str {
-        &self.str[self.cursor..]
+        &self.str[self.cursor..self.back]
+    }
+
+    /// Convert a [`StrTokensLocation`] obtained from this [`StrTokens`]
+    /// (via [`Tokens::location`]) into a 1-based `(line, column)` pair.
+    ///
+    /// This scans the consumed prefix up to the location counting `\n`, so
+    /// it's worth calling only when an error actually needs reporting
+    /// rather than on every combinator step.
+    pub fn line_col(&self, location: StrTokensLocation) -> (usize, usize) {
+        let consumed = &self.str[..location.0];
+
+        let mut line = 1;
+        let mut line_start = 0;
+        for (idx, ch) in consumed.char_indices() {
+            if ch == '\n' {
+                line += 1;
+                line_start = idx + 1;
+            }
+        }
+
+        let col = consumed[line_start..].chars().count() + 1;
+        (line, col)
     }
 }
 
@@ -102,37 +164,104 @@ impl <'a> From<StrTokens<'a>> for &'a str {
 impl <'a> Iterator for StrTokens<'a> {
     type Item = char;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.cursor == self.str.len() {
+        if self.cursor == self.back {
             return None;
         }
 
-        // Cursor should always start at a valid char boundary.
-        // So, we just find the next char boundary and return the
-        // char between those two.
-        let mut next_char_boundary = self.cursor + 1;
-        while !self.str.is_char_boundary( next_char_boundary) {
-            next_char_boundary += 1;
-        }
+        let bytes = self.str.as_bytes();
+        let width = utf8_char_width(bytes[self.cursor]);
 
-        // We have to go to &str and then char. Unchecked because we know
-        // that we are on a valid boundary. There's probably a quicker way..
-        let next_char = unsafe {
-            self.str.get_unchecked(self.cursor..next_char_boundary)
-        }.chars().next().unwrap();
+        // Safe because `StrTokens` is always seeded from a valid `&str`, so
+        // the continuation bytes following a lead byte at a valid char
+        // boundary are guaranteed to be well-formed; no need to revalidate.
+        let next_char = unsafe { decode_utf8_char(bytes, self.cursor, width) };
 
-        self.cursor = next_char_boundary;
+        self.cursor += width;
         Some(next_char)
     }
 }
 
+impl <'a> DoubleEndedIterator for StrTokens<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.cursor == self.back {
+            return None;
+        }
+
+        let bytes = self.str.as_bytes();
+        let start = utf8_prev_char_start(bytes, self.back);
+        let width = self.back - start;
+
+        // Safe for the same reason as in `next()`.
+        let prev_char = unsafe { decode_utf8_char(bytes, start, width) };
+
+        self.back = start;
+        Some(prev_char)
+    }
+}
+
+/// Walk back from `end` over UTF-8 continuation bytes (`0x80..=0xBF`) to
+/// find the byte offset where the final char before `end` starts.
+fn utf8_prev_char_start(bytes: &[u8], end: usize) -> usize {
+    let mut start = end - 1;
+    while bytes[start] & 0xC0 == 0x80 {
+        start -= 1;
+    }
+    start
+}
+
+/// The number of bytes in the UTF-8 encoding of the char starting with
+/// `lead`, derived from the high bits of the lead byte.
+fn utf8_char_width(lead: u8) -> usize {
+    match lead {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
+}
+
+/// Decode the `width`-byte UTF-8 encoded char starting at `cursor`.
+///
+/// # Safety
+/// The caller must guarantee that `width` well-formed UTF-8 bytes are
+/// present starting at `cursor` (true for any lead byte and width found at
+/// a valid char boundary in a real `&str`).
+unsafe fn decode_utf8_char(bytes: &[u8], cursor: usize, width: usize) -> char {
+    let b0 = *bytes.get_unchecked(cursor) as u32;
+    let cp = match width {
+        1 => b0,
+        2 => {
+            let b1 = *bytes.get_unchecked(cursor + 1) as u32;
+            (b0 & 0x1F) << 6 | (b1 & 0x3F)
+        }
+        3 => {
+            let b1 = *bytes.get_unchecked(cursor + 1) as u32;
+            let b2 = *bytes.get_unchecked(cursor + 2) as u32;
+            (b0 & 0x0F) << 12 | (b1 & 0x3F) << 6 | (b2 & 0x3F)
+        }
+        _ => {
+            let b1 = *bytes.get_unchecked(cursor + 1) as u32;
+            let b2 = *bytes.get_unchecked(cursor + 2) as u32;
+            let b3 = *bytes.get_unchecked(cursor + 3) as u32;
+            (b0 & 0x07) << 18 | (b1 & 0x3F) << 12 | (b2 & 0x3F) << 6 | (b3 & 0x3F)
+        }
+    };
+    char::from_u32_unchecked(cp)
+}
+
 impl <'a> Tokens for StrTokens<'a> {
+    type Location = StrTokensLocation;
     type CheckPoint = StrTokensCheckpoint;
 
+    fn location(&self) -> Self::Location {
+        StrTokensLocation(self.cursor)
+    }
     fn save_checkpoint(&self) -> Self::CheckPoint {
-        StrTokensCheckpoint(self.cursor)
+        StrTokensCheckpoint(self.cursor, self.back)
     }
     fn rewind_to_checkpoint(&mut self, checkpoint: Self::CheckPoint) {
         self.cursor = checkpoint.0;
+        self.back = checkpoint.1;
     }
 }
 
@@ -147,8 +276,154 @@ impl <'a> IntoTokens<char> for &'a str {
     type Tokens = StrTokens<'a>;
     fn into_tokens(self: Self) -> Self::Tokens {
         StrTokens {
+            back: self.len(),
             str: self,
             cursor: 0,
         }
     }
-}
\ No newline at end of file
+}
+
+/// This is what you get back if you call `into_tokens()` on an
+/// [`IterTokens`], or construct one directly via [`IterTokens::new`]. Unlike
+/// [`SliceTokens`]/[`StrTokens`], it doesn't need the whole input up front;
+/// it wraps any `Iterator` and only buffers consumed items while a
+/// checkpoint is outstanding, so it can parse streaming sources (file
+/// readers, channel receivers, generators, ...) that don't fit the
+/// `&[T]`/`&str` model.
+pub struct IterTokens<I: Iterator> where I::Item: Clone {
+    iter: I,
+    // Buffered items, starting from logical position `base`. Items are
+    // only added while at least one checkpoint is outstanding, and are kept
+    // until `pos` has caught up with the end of the buffer.
+    buffer: VecDeque<I::Item>,
+    // Logical position (items pulled from `iter`) of `buffer[0]`.
+    base: usize,
+    // Logical position that `next()` will next yield.
+    pos: usize,
+    // Shared count of outstanding checkpoints, decremented as each
+    // `IterTokensCheckpoint` is dropped (whether via rewind or just being
+    // discarded).
+    live_checkpoints: Rc<Cell<usize>>,
+}
+
+pub struct IterTokensCheckpoint {
+    pos: usize,
+    live_checkpoints: Rc<Cell<usize>>,
+}
+
+impl Drop for IterTokensCheckpoint {
+    fn drop(&mut self) {
+        self.live_checkpoints.set(self.live_checkpoints.get() - 1);
+    }
+}
+
+impl <I: Iterator> IterTokens<I> where I::Item: Clone {
+    /// Wrap any iterator up so that it can be parsed via the [`Tokens`]
+    /// interface.
+    pub fn new(iter: I) -> Self {
+        IterTokens {
+            iter,
+            buffer: VecDeque::new(),
+            base: 0,
+            pos: 0,
+            live_checkpoints: Rc::new(Cell::new(0)),
+        }
+    }
+}
+
+impl <I: Iterator> Iterator for IterTokens<I> where I::Item: Clone {
+    type Item = I::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = if self.pos < self.base + self.buffer.len() {
+            // Replaying something we've already pulled, from before a rewind.
+            self.buffer[self.pos - self.base].clone()
+        } else {
+            let item = self.iter.next()?;
+            if self.live_checkpoints.get() > 0 {
+                self.buffer.push_back(item.clone());
+            }
+            item
+        };
+        self.pos += 1;
+
+        // Once no checkpoints are outstanding, nothing before `pos` can
+        // ever be rewound to again. But we must wait until `pos` has
+        // actually caught up with the end of the buffer before dropping
+        // it - otherwise we'd throw away buffered items we're still in
+        // the middle of replaying after a rewind.
+        if self.live_checkpoints.get() == 0 && self.pos >= self.base + self.buffer.len() {
+            self.buffer.clear();
+            self.base = self.pos;
+        }
+
+        Some(item)
+    }
+}
+
+impl <I: Iterator> Tokens for IterTokens<I> where I::Item: Clone {
+    type Location = usize;
+    type CheckPoint = IterTokensCheckpoint;
+
+    fn location(&self) -> Self::Location {
+        self.pos
+    }
+    fn save_checkpoint(&self) -> Self::CheckPoint {
+        self.live_checkpoints.set(self.live_checkpoints.get() + 1);
+        IterTokensCheckpoint {
+            pos: self.pos,
+            live_checkpoints: Rc::clone(&self.live_checkpoints),
+        }
+    }
+    fn rewind_to_checkpoint(&mut self, checkpoint: Self::CheckPoint) {
+        self.pos = checkpoint.pos;
+    }
+}
+
+impl <I: Iterator> IntoTokens<I::Item> for IterTokens<I> where I::Item: Clone {
+    type Tokens = Self;
+    fn into_tokens(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod iter_tokens_tests {
+    use super::*;
+
+    #[test]
+    fn rewind_after_unbuffered_consumption_replays_every_item() {
+        let mut toks = IterTokens::new("ABCD".chars());
+
+        // Consume an item with no checkpoint outstanding, so it's dropped
+        // from the buffer immediately rather than retained.
+        assert_eq!(toks.next(), Some('A'));
+
+        let checkpoint = toks.save_checkpoint();
+        assert_eq!(toks.next(), Some('B'));
+        toks.rewind_to_checkpoint(checkpoint);
+
+        // 'B' must be replayed, not skipped.
+        assert_eq!(toks.next(), Some('B'));
+        assert_eq!(toks.next(), Some('C'));
+        assert_eq!(toks.next(), Some('D'));
+        assert_eq!(toks.next(), None);
+    }
+
+    #[test]
+    fn rewind_replays_every_buffered_item_even_after_checkpoint_drops() {
+        let mut toks = IterTokens::new("ABCD".chars());
+
+        let checkpoint = toks.save_checkpoint();
+        assert_eq!(toks.next(), Some('A'));
+        assert_eq!(toks.next(), Some('B'));
+        toks.rewind_to_checkpoint(checkpoint);
+
+        // The checkpoint is now gone, but every buffered item from 'A'
+        // onward must still be replayed in order, not just the first one.
+        assert_eq!(toks.next(), Some('A'));
+        assert_eq!(toks.next(), Some('B'));
+        assert_eq!(toks.next(), Some('C'));
+        assert_eq!(toks.next(), Some('D'));
+        assert_eq!(toks.next(), None);
+    }
+}